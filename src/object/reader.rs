@@ -19,6 +19,7 @@ use std::task::Context;
 use std::task::Poll;
 
 use bytes::Bytes;
+use bytes::BytesMut;
 use futures::AsyncRead;
 use futures::AsyncSeek;
 use futures::Stream;
@@ -30,6 +31,23 @@ use crate::ObjectMetadata;
 use crate::OpRead;
 use crate::OpStat;
 
+mod buffer;
+pub use buffer::ObjectBufReader;
+
+mod transform;
+pub use transform::ChecksumAlgorithm;
+pub use transform::ChecksumVerify;
+pub use transform::GzipDecode;
+pub use transform::Inspect;
+pub use transform::Transform;
+pub use transform::ZstdDecode;
+
+mod prefetch;
+use prefetch::PrefetchReader;
+
+mod take;
+pub use take::ObjectTake;
+
 /// ObjectReader is the public API for users.
 ///
 /// # Usage
@@ -54,6 +72,14 @@ use crate::OpStat;
 /// Besides, `Stream` **COULD** reduce an extra copy if underlying reader is
 /// stream based (like services s3, azure which based on HTTP).
 ///
+/// Users doing many small reads (for example parsing newline-delimited
+/// data) should wrap the reader with [`ObjectReader::buffered`] first so
+/// those reads are served from an in-memory buffer instead of the inner
+/// reader directly.
+///
+/// [`ObjectReader::take`] caps the total bytes returned by an
+/// already-open reader without issuing a new ranged `OpRead`.
+///
 /// # Notes
 ///
 /// All implementions of ObjectReader should be `zero cost`. In our cases,
@@ -87,6 +113,9 @@ use crate::OpStat;
 /// If there is a hint that `ReadIsStreamable`, we will use existing reader
 /// directly. Otherwise, we will use transform this reader as a stream.
 ///
+/// The `Bytes` granularity produced this way can be tuned via
+/// `OpRead::with_chunk_size`, defaulting to 256KiB.
+///
 /// ## Consume instead of Drop
 ///
 /// Normally, if reader is seekable, we need to drop current reader and start
@@ -97,8 +126,43 @@ use crate::OpStat;
 /// can consume it.
 ///
 /// In this way, we can reduce the extra cost of dropping reader.
+///
+/// ## Prefetch
+///
+/// Callers can opt into background read-ahead via `OpRead::with_prefetch`.
+/// Because it's an explicit opt-in, it takes priority over the
+/// `ReadIsSeekable` hint: HTTP-backed services like S3 and Azure set that
+/// hint (their accessor supports native range reads) but each such range
+/// read is still a network round-trip, which is exactly the latency
+/// prefetch exists to hide. It is off by default to preserve the
+/// zero-cost behavior described above.
+///
+/// ## Transforms
+///
+/// Users can attach ordered, composable [`Transform`]s via
+/// [`ObjectReader::with_transform`] (on-the-fly decompression, checksum
+/// verification, progress inspection, ...). Transforms run left-to-right
+/// as bytes flow out of `poll_read` / `poll_next`.
+///
+/// Attaching any [`Transform`] disables seeking. A decompressing transform
+/// breaks the 1:1 mapping between offsets and the underlying object, but
+/// even a byte-preserving one (for example [`ChecksumVerify`]) carries
+/// state derived from every byte seen so far; a seek would leave that
+/// state describing bytes the caller never actually ended up reading in
+/// order, and there's no general way to rewind it.
 pub struct ObjectReader {
     inner: OutputBytesReader,
+    transforms: Vec<Box<dyn Transform>>,
+    /// Size of the scratch buffer used to pull raw bytes from the inner
+    /// reader before feeding them through the transform chain, sized from
+    /// `OpRead::chunk_size` just like the non-streamable fallback path.
+    scratch_size: usize,
+    /// Bytes already produced by the transform chain but not yet returned
+    /// to the caller.
+    pending: BytesMut,
+    /// Set once the inner reader has reached EOF and every transform has
+    /// been asked to flush its trailing state.
+    eof: bool,
 }
 
 impl ObjectReader {
@@ -116,8 +180,33 @@ impl ObjectReader {
         op: OpRead,
     ) -> Result<Self> {
         let acc_meta = acc.metadata();
+        let chunk_size = op.chunk_size();
 
-        let r = if acc_meta.hints().contains(AccessorHint::ReadIsSeekable) {
+        // Prefetch is an explicit opt-in (`OpRead::with_prefetch`), so it
+        // takes priority over the `ReadIsSeekable` hint: services like S3
+        // and Azure set that hint (their accessor already supports native
+        // range reads), but each such range read is still an HTTP
+        // round-trip, which is exactly the latency prefetch exists to
+        // hide. Gating this behind "not seekable" would mean the services
+        // the request calls out by name never actually get it.
+        let r = if let Some((chunks, chunk_size)) = op.prefetch() {
+            let total_size = get_total_size(acc.clone(), path, meta.clone()).await?;
+            let (offset, total_size) = match (op.range().offset(), op.range().size()) {
+                (Some(offset), Some(size)) => (offset, (offset + size).min(total_size)),
+                (Some(offset), None) => (offset, total_size),
+                (None, Some(size)) if size <= total_size => (total_size - size, total_size),
+                (None, _) => (0, total_size),
+            };
+
+            Box::new(PrefetchReader::new(
+                acc,
+                path.to_string(),
+                total_size,
+                chunks,
+                chunk_size,
+                offset,
+            )) as OutputBytesReader
+        } else if acc_meta.hints().contains(AccessorHint::ReadIsSeekable) {
             let (_, r) = acc.read(path, op).await?;
             r
         } else {
@@ -146,25 +235,123 @@ impl ObjectReader {
         let r = if acc_meta.hints().contains(AccessorHint::ReadIsStreamable) {
             r
         } else {
-            // Make this capacity configurable.
-            Box::new(into_seekable_stream(r, 256 * 1024))
+            Box::new(into_seekable_stream(r, chunk_size))
         };
 
-        Ok(ObjectReader { inner: r })
+        Ok(ObjectReader {
+            inner: r,
+            transforms: Vec::new(),
+            scratch_size: chunk_size,
+            pending: BytesMut::new(),
+            eof: false,
+        })
+    }
+
+    /// Wrap this reader with an internal buffer of `capacity` bytes.
+    ///
+    /// The returned [`ObjectBufReader`] implements `futures::AsyncBufRead`,
+    /// so callers doing many small reads (for example parsing
+    /// newline-delimited CSV/NDJSON/logs stored as an object) can use
+    /// `AsyncBufReadExt::read_until` or `AsyncBufReadExt::lines` instead of
+    /// reassembling chunks by hand, while still only issuing large,
+    /// infrequent reads against the inner reader.
+    pub fn buffered(self, capacity: usize) -> ObjectBufReader {
+        ObjectBufReader::new(self, capacity)
+    }
+
+    /// Attach a [`Transform`] to the end of this reader's transform chain.
+    ///
+    /// Transforms run left-to-right in the order they're attached. See the
+    /// "Transforms" section on [`ObjectReader`] for the seeking caveat.
+    pub fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    /// Wrap this reader so at most `limit` bytes are returned before EOF.
+    ///
+    /// Unlike issuing a new ranged `OpRead`, this composes on an
+    /// already-open reader, so it can cap a reader that's already been
+    /// seeked to a particular position.
+    pub fn take(self, limit: u64) -> ObjectTake {
+        ObjectTake::new(self, limit)
+    }
+
+    fn seekable(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Pull more transformed bytes into `self.pending`, reading one scratch
+    /// buffer at a time from the inner reader until the transform chain
+    /// produces output or the inner reader reaches EOF.
+    ///
+    /// On EOF from the inner reader, every transform is given a chance to
+    /// flush trailing state (e.g. a final decompression frame or a
+    /// checksum comparison), and its flushed output is threaded through
+    /// the rest of the chain just like regular bytes.
+    fn poll_transform(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // A single scratch read isn't enough: a transform that buffers
+        // input internally (e.g. a streaming decompressor at the start of
+        // a frame) may legitimately emit zero bytes for a given chunk. Keep
+        // pulling from the inner reader until we have something to return
+        // or the inner reader has actually reached EOF.
+        while !self.eof && self.pending.is_empty() {
+            let mut scratch = vec![0u8; self.scratch_size];
+            let n = futures::ready!(self.inner.poll_read(cx, &mut scratch))?;
+
+            let mut current = BytesMut::from(&scratch[..n]);
+            for transform in self.transforms.iter_mut() {
+                let mut out = BytesMut::new();
+                transform.transform(&current, &mut out)?;
+                if n == 0 {
+                    transform.finish(&mut out)?;
+                }
+                current = out;
+            }
+
+            if n == 0 {
+                self.eof = true;
+            }
+            self.pending = current;
+        }
+
+        Poll::Ready(Ok(()))
     }
 }
 
 impl OutputBytesRead for ObjectReader {
     fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
-        self.inner.poll_read(cx, buf)
+        if self.transforms.is_empty() {
+            return self.inner.poll_read(cx, buf);
+        }
+
+        futures::ready!(self.poll_transform(cx))?;
+        let n = std::cmp::min(buf.len(), self.pending.len());
+        buf[..n].copy_from_slice(&self.pending.split_to(n));
+        Poll::Ready(Ok(n))
     }
 
     fn poll_seek(&mut self, cx: &mut Context<'_>, pos: io::SeekFrom) -> Poll<io::Result<u64>> {
+        if !self.seekable() {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "seek is not supported once a transform is attached",
+            )));
+        }
+
         self.inner.poll_seek(cx, pos)
     }
 
     fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Bytes>>> {
-        self.inner.poll_next(cx)
+        if self.transforms.is_empty() {
+            return self.inner.poll_next(cx);
+        }
+
+        futures::ready!(self.poll_transform(cx))?;
+        if self.pending.is_empty() {
+            return Poll::Ready(None);
+        }
+        Poll::Ready(Some(Ok(self.pending.split().freeze())))
     }
 }
 
@@ -174,7 +361,7 @@ impl AsyncRead for ObjectReader {
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<io::Result<usize>> {
-        Pin::new(&mut self.inner).poll_read(cx, buf)
+        OutputBytesRead::poll_read(&mut *self, cx, buf)
     }
 }
 
@@ -184,7 +371,7 @@ impl AsyncSeek for ObjectReader {
         cx: &mut Context<'_>,
         pos: io::SeekFrom,
     ) -> Poll<io::Result<u64>> {
-        Pin::new(&mut self.inner).poll_seek(cx, pos)
+        OutputBytesRead::poll_seek(&mut *self, cx, pos)
     }
 }
 
@@ -192,7 +379,7 @@ impl Stream for ObjectReader {
     type Item = io::Result<Bytes>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        Pin::new(&mut self.inner).poll_next(cx)
+        OutputBytesRead::poll_next(&mut *self, cx)
     }
 }
 
@@ -0,0 +1,170 @@
+// Copyright 2022 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp;
+use std::io;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use futures::AsyncBufRead;
+use futures::AsyncRead;
+use futures::AsyncSeek;
+
+use super::ObjectReader;
+
+/// ObjectBufReader is returned by [`ObjectReader::buffered`].
+///
+/// It wraps an [`ObjectReader`] with an in-memory buffer so that callers
+/// doing many small reads only pay for a large, infrequent read against the
+/// inner reader. It's modeled closely after `futures::io::BufReader`.
+///
+/// # Seeking
+///
+/// Seeking discards whatever is currently buffered and re-fills on the next
+/// read. As an optimization, a forward seek that lands inside the current
+/// buffer is served by consuming the buffer instead, mirroring the
+/// "Consume instead of Drop" trick used elsewhere in [`ObjectReader`] so a
+/// short forward seek doesn't force the underlying connection to be
+/// reopened.
+pub struct ObjectBufReader {
+    inner: ObjectReader,
+    buf: Box<[u8]>,
+    pos: usize,
+    cap: usize,
+}
+
+impl ObjectBufReader {
+    /// Create a new `ObjectBufReader` with the given buffer `capacity`.
+    pub(super) fn new(inner: ObjectReader, capacity: usize) -> Self {
+        Self {
+            inner,
+            buf: vec![0; capacity].into_boxed_slice(),
+            pos: 0,
+            cap: 0,
+        }
+    }
+
+    /// Consume `self` and return the inner [`ObjectReader`].
+    ///
+    /// Any currently buffered data is dropped.
+    pub fn into_inner(self) -> ObjectReader {
+        self.inner
+    }
+
+    fn buffer(&self) -> &[u8] {
+        &self.buf[self.pos..self.cap]
+    }
+
+    fn discard_buffer(&mut self) {
+        self.pos = 0;
+        self.cap = 0;
+    }
+}
+
+impl AsyncRead for ObjectBufReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        // If we don't have any buffered data and we're doing a massive read
+        // (larger than our internal buffer), bypass our internal buffer
+        // entirely.
+        if self.pos == self.cap && buf.len() >= self.buf.len() {
+            self.discard_buffer();
+            return Pin::new(&mut self.inner).poll_read(cx, buf);
+        }
+
+        let rem = futures::ready!(self.as_mut().poll_fill_buf(cx))?;
+        let amt = cmp::min(rem.len(), buf.len());
+        buf[..amt].copy_from_slice(&rem[..amt]);
+        self.consume(amt);
+        Poll::Ready(Ok(amt))
+    }
+}
+
+impl AsyncBufRead for ObjectBufReader {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+
+        if this.pos >= this.cap {
+            debug_assert!(this.pos == this.cap);
+
+            let n = futures::ready!(Pin::new(&mut this.inner).poll_read(cx, &mut this.buf))?;
+            this.pos = 0;
+            this.cap = n;
+        }
+
+        Poll::Ready(Ok(this.buffer()))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        this.pos = cmp::min(this.pos + amt, this.cap);
+    }
+}
+
+impl AsyncSeek for ObjectBufReader {
+    fn poll_seek(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: io::SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        let result: u64;
+
+        if let io::SeekFrom::Current(n) = pos {
+            let remainder = (self.cap - self.pos) as i64;
+            // If the seek is forward but still lands inside the currently
+            // buffered region, serve it by consuming the buffer. This
+            // avoids re-filling (and therefore re-requesting data from) the
+            // inner reader for small forward seeks, mirroring the "Consume
+            // instead of Drop" optimization used elsewhere.
+            if n >= 0 && n <= remainder {
+                self.as_mut().consume(n as usize);
+                // The inner reader's physical position sits at the end of
+                // our buffered region, i.e. `remainder` bytes ahead of our
+                // logical position before this seek. Subtract what's still
+                // unconsumed after the seek to recover the caller's new
+                // logical position instead of reporting the inner
+                // reader's (unchanged) physical one.
+                let physical_pos = futures::ready!(
+                    Pin::new(&mut self.inner).poll_seek(cx, io::SeekFrom::Current(0))
+                )?;
+                let logical_pos = physical_pos - (remainder - n) as u64;
+                return Poll::Ready(Ok(logical_pos));
+            }
+
+            if let Some(offset) = n.checked_sub(remainder) {
+                result = futures::ready!(
+                    Pin::new(&mut self.inner).poll_seek(cx, io::SeekFrom::Current(offset))
+                )?;
+            } else {
+                // seek backwards by our remainder, and then by the offset.
+                futures::ready!(
+                    Pin::new(&mut self.inner).poll_seek(cx, io::SeekFrom::Current(-remainder))
+                )?;
+                self.as_mut().discard_buffer();
+                result = futures::ready!(
+                    Pin::new(&mut self.inner).poll_seek(cx, io::SeekFrom::Current(n))
+                )?;
+            }
+        } else {
+            result = futures::ready!(Pin::new(&mut self.inner).poll_seek(cx, pos))?;
+        }
+
+        self.discard_buffer();
+        Poll::Ready(Ok(result))
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright 2022 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::AsyncReadExt;
+use futures::FutureExt;
+
+use crate::raw::*;
+use crate::OpRead;
+
+/// A single range fetch somewhere between "just issued" and "bytes in
+/// hand". Kept as an enum (rather than polling-to-completion-and-drop) so
+/// [`PrefetchReader::drive_pending`] can advance every in-flight fetch each
+/// call without re-polling one that's already resolved.
+enum Slot {
+    Pending(BoxFuture<'static, io::Result<Bytes>>),
+    Ready(io::Result<Bytes>),
+}
+
+/// PrefetchReader keeps a bounded window of range requests in flight ahead
+/// of the current read position, hiding the per-request latency of
+/// HTTP-backed services (S3, Azure, ...) from a sequential consumer.
+///
+/// `ring` holds bytes that have already been fetched but not yet returned
+/// to the caller; `offset` is the absolute position of `ring[0]` in the
+/// object and `pos` is the absolute position of the next byte the caller
+/// will read. `pending` is a queue of in-flight range fetches, in order,
+/// covering `[offset + ring.len(), next_fetch)`.
+pub struct PrefetchReader {
+    acc: Arc<dyn Accessor>,
+    path: String,
+    total_size: u64,
+    chunk_size: usize,
+    depth: usize,
+
+    ring: Vec<u8>,
+    offset: u64,
+    pos: u64,
+    next_fetch: u64,
+    pending: VecDeque<Slot>,
+}
+
+impl PrefetchReader {
+    /// Create a new `PrefetchReader` starting at `start`, reading up to
+    /// `total_size`, keeping `chunks` fetches of `chunk_size` bytes each in
+    /// flight.
+    pub fn new(
+        acc: Arc<dyn Accessor>,
+        path: String,
+        total_size: u64,
+        chunks: usize,
+        chunk_size: usize,
+        start: u64,
+    ) -> Self {
+        let mut r = Self {
+            acc,
+            path,
+            total_size,
+            chunk_size: chunk_size.max(1),
+            depth: chunks.max(1),
+            ring: Vec::new(),
+            offset: start,
+            pos: start,
+            next_fetch: start,
+            pending: VecDeque::new(),
+        };
+        r.fill_window();
+        r
+    }
+
+    fn fetch(&self, offset: u64, size: u64) -> BoxFuture<'static, io::Result<Bytes>> {
+        let acc = self.acc.clone();
+        let path = self.path.clone();
+
+        async move {
+            let (_, mut r) = acc
+                .read(&path, OpRead::new().with_range((offset, offset + size)))
+                .await
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+            let mut buf = Vec::with_capacity(size as usize);
+            r.read_to_end(&mut buf).await?;
+            Ok(Bytes::from(buf))
+        }
+        .boxed()
+    }
+
+    /// Issue new range fetches until `depth` fetches are in flight or we've
+    /// reached the end of the object.
+    fn fill_window(&mut self) {
+        while self.pending.len() < self.depth && self.next_fetch < self.total_size {
+            let size = (self.total_size - self.next_fetch).min(self.chunk_size as u64);
+            self.pending
+                .push_back(Slot::Pending(self.fetch(self.next_fetch, size)));
+            self.next_fetch += size;
+        }
+    }
+
+    /// Poll every still-in-flight fetch once, not just the one at the front
+    /// of the queue.
+    ///
+    /// A `BoxFuture` makes no progress until it's polled, so only ever
+    /// polling the front would leave the rest of the window sitting idle
+    /// until their turn, collapsing the window down to depth 1 in practice.
+    /// Polling them all here lets the back of the queue keep progressing
+    /// while the front is being drained into the ring.
+    fn drive_pending(&mut self, cx: &mut Context<'_>) {
+        for slot in self.pending.iter_mut() {
+            if let Slot::Pending(fut) = slot {
+                if let Poll::Ready(result) = fut.as_mut().poll(cx) {
+                    *slot = Slot::Ready(result);
+                }
+            }
+        }
+    }
+
+    /// Discard the buffered ring and every in-flight fetch, restarting the
+    /// window at `pos`.
+    fn restart_at(&mut self, pos: u64) {
+        self.ring.clear();
+        self.pending.clear();
+        self.offset = pos;
+        self.pos = pos;
+        self.next_fetch = pos;
+        self.fill_window();
+    }
+}
+
+impl OutputBytesRead for PrefetchReader {
+    fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        loop {
+            let ring_end = self.offset + self.ring.len() as u64;
+
+            if self.pos < ring_end {
+                let start = (self.pos - self.offset) as usize;
+                let n = buf.len().min(self.ring.len() - start);
+                buf[..n].copy_from_slice(&self.ring[start..start + n]);
+
+                // Drop the consumed prefix so the ring doesn't grow
+                // unbounded over a long sequential read.
+                self.ring.drain(..start + n);
+                self.offset += (start + n) as u64;
+                self.pos += n as u64;
+
+                return Poll::Ready(Ok(n));
+            }
+
+            if self.pos >= self.total_size {
+                return Poll::Ready(Ok(0));
+            }
+
+            self.drive_pending(cx);
+
+            match self.pending.front() {
+                Some(Slot::Ready(_)) => {
+                    let bytes = match self.pending.pop_front() {
+                        Some(Slot::Ready(result)) => result?,
+                        _ => unreachable!(),
+                    };
+                    self.ring.extend_from_slice(&bytes);
+                    self.fill_window();
+                }
+                // The fetch at the front hasn't resolved yet, but
+                // `drive_pending` above already registered this task with
+                // every in-flight future's waker, so we'll be polled again
+                // once any of them makes progress.
+                Some(Slot::Pending(_)) => return Poll::Pending,
+                // We're not at EOF but have nothing in flight and nothing
+                // buffered: every fetch up to `total_size` has been issued,
+                // but the fetches returned fewer bytes than requested.
+                None => {
+                    self.fill_window();
+                    if self.pending.is_empty() {
+                        return Poll::Ready(Ok(0));
+                    }
+                }
+            }
+        }
+    }
+
+    fn poll_seek(&mut self, _cx: &mut Context<'_>, pos: io::SeekFrom) -> Poll<io::Result<u64>> {
+        let target = match pos {
+            io::SeekFrom::Start(n) => n,
+            io::SeekFrom::End(n) => (self.total_size as i64 + n).max(0) as u64,
+            io::SeekFrom::Current(n) => (self.pos as i64 + n).max(0) as u64,
+        };
+
+        // A forward seek that still lands inside the buffered-or-in-flight
+        // window just advances `pos`; the existing fetches remain valid.
+        if target >= self.pos && target < self.next_fetch {
+            self.pos = target;
+            return Poll::Ready(Ok(target));
+        }
+
+        self.restart_at(target);
+        Poll::Ready(Ok(target))
+    }
+
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Bytes>>> {
+        let mut buf = vec![0u8; self.chunk_size];
+        match futures::ready!(self.poll_read(cx, &mut buf)) {
+            Ok(0) => Poll::Ready(None),
+            Ok(n) => {
+                buf.truncate(n);
+                Poll::Ready(Some(Ok(Bytes::from(buf))))
+            }
+            Err(e) => Poll::Ready(Some(Err(e))),
+        }
+    }
+}
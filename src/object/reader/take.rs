@@ -0,0 +1,133 @@
+// Copyright 2022 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp;
+use std::io;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+use bytes::Bytes;
+use futures::AsyncRead;
+use futures::AsyncSeek;
+use futures::Stream;
+
+use crate::raw::*;
+
+use super::ObjectReader;
+
+/// ObjectTake is returned by [`ObjectReader::take`].
+///
+/// It caps the total bytes returned from `poll_read` / `poll_next` to at
+/// most `limit`, signalling EOF once that many bytes have flowed through,
+/// without issuing a new ranged `OpRead`. This is handy when a caller has a
+/// reader positioned via seek and wants to read exactly the next record of
+/// known length, or to enforce a decode-bomb limit on top of a
+/// decompression transform.
+///
+/// `limit` always tracks bytes remaining *from the current position*: a
+/// seek doesn't touch it, since seeking doesn't consume any of the
+/// allowance.
+pub struct ObjectTake {
+    inner: ObjectReader,
+    limit: u64,
+}
+
+impl ObjectTake {
+    /// Create a new `ObjectTake` capping `inner` to `limit` bytes.
+    pub(super) fn new(inner: ObjectReader, limit: u64) -> Self {
+        Self { inner, limit }
+    }
+
+    /// Consume `self` and return the inner [`ObjectReader`].
+    pub fn into_inner(self) -> ObjectReader {
+        self.inner
+    }
+
+    /// Get the number of bytes still allowed to be read.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Set the number of bytes still allowed to be read, so the same
+    /// reader can be reused for successive fixed-size records.
+    pub fn set_limit(&mut self, limit: u64) {
+        self.limit = limit;
+    }
+}
+
+impl OutputBytesRead for ObjectTake {
+    fn poll_read(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        if self.limit == 0 {
+            return Poll::Ready(Ok(0));
+        }
+
+        let max = cmp::min(buf.len() as u64, self.limit) as usize;
+        let n = futures::ready!(self.inner.poll_read(cx, &mut buf[..max]))?;
+        self.limit -= n as u64;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_seek(&mut self, cx: &mut Context<'_>, pos: io::SeekFrom) -> Poll<io::Result<u64>> {
+        // `limit` is always "bytes remaining from here", so a seek doesn't
+        // need to adjust it: nothing has been consumed by seeking.
+        self.inner.poll_seek(cx, pos)
+    }
+
+    fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<io::Result<Bytes>>> {
+        if self.limit == 0 {
+            return Poll::Ready(None);
+        }
+
+        match futures::ready!(self.inner.poll_next(cx)) {
+            None => Poll::Ready(None),
+            Some(Err(e)) => Poll::Ready(Some(Err(e))),
+            Some(Ok(mut bytes)) => {
+                if bytes.len() as u64 > self.limit {
+                    bytes.truncate(self.limit as usize);
+                }
+                self.limit -= bytes.len() as u64;
+                Poll::Ready(Some(Ok(bytes)))
+            }
+        }
+    }
+}
+
+impl AsyncRead for ObjectTake {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        OutputBytesRead::poll_read(&mut *self, cx, buf)
+    }
+}
+
+impl AsyncSeek for ObjectTake {
+    fn poll_seek(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: io::SeekFrom,
+    ) -> Poll<io::Result<u64>> {
+        OutputBytesRead::poll_seek(&mut *self, cx, pos)
+    }
+}
+
+impl Stream for ObjectTake {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        OutputBytesRead::poll_next(&mut *self, cx)
+    }
+}
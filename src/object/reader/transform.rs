@@ -0,0 +1,417 @@
+// Copyright 2022 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::io;
+
+use bytes::BufMut;
+use bytes::BytesMut;
+use crc32fast::Hasher as Crc32Hasher;
+use flate2::Decompress;
+use flate2::FlushDecompress;
+use flate2::Status;
+use md5::Digest;
+use md5::Md5;
+use sha2::Sha256;
+
+/// Transform lets users attach ordered, composable byte transforms to an
+/// [`super::ObjectReader`] that run as bytes flow out of `poll_read` /
+/// `poll_next`.
+///
+/// Transforms run left-to-right: the output of one transform becomes the
+/// input of the next. Implementations may buffer internal state (for
+/// example a partially decoded compression frame) between calls.
+pub trait Transform: Send + Sync {
+    /// Transform `input`, appending any produced bytes to `out`.
+    ///
+    /// `transform` may turn all, part, or none of `input` into output in a
+    /// single call. The next call only receives the bytes that arrive after
+    /// `input`, so an implementation that doesn't use all of `input` must
+    /// keep the unused bytes itself.
+    fn transform(&mut self, input: &[u8], out: &mut BytesMut) -> io::Result<()>;
+
+    /// Called once the inner reader has reached EOF, giving transforms
+    /// with trailing state (for example a checksum or a compressor's final
+    /// frame) a chance to flush or validate it.
+    ///
+    /// The default implementation does nothing.
+    fn finish(&mut self, out: &mut BytesMut) -> io::Result<()> {
+        let _ = out;
+        Ok(())
+    }
+}
+
+/// Parsing state for a gzip member: a 10+ byte header (with optional
+/// variable-length extensions), a raw DEFLATE body, and an 8 byte trailer
+/// (CRC32 + ISIZE of the uncompressed data).
+enum GzipState {
+    Header,
+    Body,
+    Trailer,
+    Done,
+}
+
+/// GzipDecode streams gzip-compressed bytes into plaintext.
+///
+/// Reading a `foo.json.gz` object through a reader with this transform
+/// attached yields the decompressed JSON. Unlike raw DEFLATE, gzip wraps
+/// the compressed body in a header (parsed and skipped here) and a
+/// trailing CRC32/ISIZE, which is verified once the inner reader reaches
+/// EOF.
+pub struct GzipDecode {
+    state: GzipState,
+    header_buf: Vec<u8>,
+    // Unconsumed DEFLATE bytes left over from a call that made no progress,
+    // prepended to the next call's input instead of being dropped.
+    carry: Vec<u8>,
+    trailer_buf: Vec<u8>,
+    inflate: Decompress,
+    crc: Crc32Hasher,
+}
+
+impl Default for GzipDecode {
+    fn default() -> Self {
+        Self {
+            state: GzipState::Header,
+            header_buf: Vec::new(),
+            carry: Vec::new(),
+            trailer_buf: Vec::new(),
+            inflate: Decompress::new(false),
+            crc: Crc32Hasher::new(),
+        }
+    }
+}
+
+impl GzipDecode {
+    /// Feed DEFLATE body bytes through the inflater, tracking the running
+    /// CRC32 of the decompressed output and switching to `Trailer` once the
+    /// DEFLATE stream signals its end.
+    fn decompress_body(&mut self, input: &[u8], out: &mut BytesMut) -> io::Result<()> {
+        let staged;
+        let input: &[u8] = if self.carry.is_empty() {
+            input
+        } else {
+            self.carry.extend_from_slice(input);
+            staged = std::mem::take(&mut self.carry);
+            &staged
+        };
+
+        let mut scratch = [0u8; 16 * 1024];
+        let mut consumed = 0;
+
+        while consumed < input.len() {
+            let before_in = self.inflate.total_in();
+            let before_out = self.inflate.total_out();
+
+            let status = self
+                .inflate
+                .decompress(&input[consumed..], &mut scratch, FlushDecompress::None)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            consumed += (self.inflate.total_in() - before_in) as usize;
+            let produced = (self.inflate.total_out() - before_out) as usize;
+            self.crc.update(&scratch[..produced]);
+            out.put_slice(&scratch[..produced]);
+
+            if status == Status::StreamEnd {
+                self.state = GzipState::Trailer;
+                self.trailer_buf.extend_from_slice(&input[consumed..]);
+                return Ok(());
+            }
+
+            // Decoder made no progress on a non-empty input: it needs more
+            // bytes than we gave it (for example a DEFLATE block header
+            // split across two chunks). Stash the unconsumed remainder
+            // instead of dropping it, and let the next call prepend
+            // whatever arrives next.
+            if produced == 0 && self.inflate.total_in() - before_in == 0 {
+                self.carry = input[consumed..].to_vec();
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Try to parse a complete gzip member header from `buf`, returning its
+/// length in bytes, or `None` if `buf` doesn't hold a full header yet.
+fn gzip_header_len(buf: &[u8]) -> io::Result<Option<usize>> {
+    if buf.len() < 10 {
+        return Ok(None);
+    }
+    if buf[0] != 0x1f || buf[1] != 0x8b {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "input is not a gzip stream",
+        ));
+    }
+    if buf[2] != 8 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unsupported gzip compression method",
+        ));
+    }
+
+    let flg = buf[3];
+    let mut pos = 10;
+
+    if flg & 0x04 != 0 {
+        // FEXTRA
+        if buf.len() < pos + 2 {
+            return Ok(None);
+        }
+        let xlen = u16::from_le_bytes([buf[pos], buf[pos + 1]]) as usize;
+        pos += 2;
+        if buf.len() < pos + xlen {
+            return Ok(None);
+        }
+        pos += xlen;
+    }
+    if flg & 0x08 != 0 {
+        // FNAME: zero-terminated.
+        match buf[pos..].iter().position(|&b| b == 0) {
+            Some(i) => pos += i + 1,
+            None => return Ok(None),
+        }
+    }
+    if flg & 0x10 != 0 {
+        // FCOMMENT: zero-terminated.
+        match buf[pos..].iter().position(|&b| b == 0) {
+            Some(i) => pos += i + 1,
+            None => return Ok(None),
+        }
+    }
+    if flg & 0x02 != 0 {
+        // FHCRC
+        if buf.len() < pos + 2 {
+            return Ok(None);
+        }
+        pos += 2;
+    }
+
+    Ok(Some(pos))
+}
+
+impl Transform for GzipDecode {
+    fn transform(&mut self, input: &[u8], out: &mut BytesMut) -> io::Result<()> {
+        if matches!(self.state, GzipState::Header) {
+            self.header_buf.extend_from_slice(input);
+            match gzip_header_len(&self.header_buf)? {
+                None => return Ok(()),
+                Some(len) => {
+                    let body = self.header_buf.split_off(len);
+                    self.header_buf.clear();
+                    self.state = GzipState::Body;
+                    return self.decompress_body(&body, out);
+                }
+            }
+        }
+
+        match self.state {
+            GzipState::Body => self.decompress_body(input, out),
+            GzipState::Trailer | GzipState::Done => {
+                self.trailer_buf.extend_from_slice(input);
+                Ok(())
+            }
+            GzipState::Header => unreachable!(),
+        }
+    }
+
+    fn finish(&mut self, out: &mut BytesMut) -> io::Result<()> {
+        let _ = out;
+
+        if self.trailer_buf.len() < 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated gzip trailer",
+            ));
+        }
+
+        let expected_crc = u32::from_le_bytes(self.trailer_buf[0..4].try_into().unwrap());
+        let expected_isize = u32::from_le_bytes(self.trailer_buf[4..8].try_into().unwrap());
+        let actual_crc = self.crc.clone().finalize();
+        let actual_isize = self.inflate.total_out() as u32;
+
+        if actual_crc != expected_crc || actual_isize != expected_isize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "gzip CRC32/ISIZE mismatch at EOF",
+            ));
+        }
+
+        self.state = GzipState::Done;
+        Ok(())
+    }
+}
+
+/// ZstdDecode streams zstd-compressed bytes into plaintext.
+pub struct ZstdDecode {
+    inner: zstd::stream::raw::Decoder<'static>,
+}
+
+impl ZstdDecode {
+    /// Create a new zstd streaming decoder.
+    pub fn new() -> io::Result<Self> {
+        Ok(Self {
+            inner: zstd::stream::raw::Decoder::new()?,
+        })
+    }
+}
+
+impl Transform for ZstdDecode {
+    fn transform(&mut self, input: &[u8], out: &mut BytesMut) -> io::Result<()> {
+        use zstd::stream::raw::Operation;
+
+        let mut in_buf = zstd::stream::raw::InBuffer::around(input);
+        let mut scratch = vec![0u8; 32 * 1024];
+
+        while in_buf.pos < in_buf.src.len() {
+            let before_in = in_buf.pos;
+            let mut out_buf = zstd::stream::raw::OutBuffer::around(&mut scratch);
+            self.inner.run(&mut in_buf, &mut out_buf)?;
+            let produced = out_buf.pos();
+            out.put_slice(&scratch[..produced]);
+
+            // The decoder can buffer input towards a frame without
+            // producing output yet, but if a call consumes nothing and
+            // produces nothing, further calls with the same input won't
+            // help either: bail out instead of spinning.
+            if produced == 0 && in_buf.pos == before_in {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn finish(&mut self, out: &mut BytesMut) -> io::Result<()> {
+        use zstd::stream::raw::Operation;
+
+        // Now that no more input is coming, drain any decompressed bytes
+        // the decoder is still holding internally (for example the tail of
+        // the last frame).
+        let mut scratch = vec![0u8; 32 * 1024];
+        loop {
+            let mut in_buf = zstd::stream::raw::InBuffer::around(&[][..]);
+            let mut out_buf = zstd::stream::raw::OutBuffer::around(&mut scratch);
+            self.inner.run(&mut in_buf, &mut out_buf)?;
+            let produced = out_buf.pos();
+            if produced == 0 {
+                break;
+            }
+            out.put_slice(&scratch[..produced]);
+        }
+
+        Ok(())
+    }
+}
+
+/// Checksum algorithms supported by [`ChecksumVerify`].
+pub enum ChecksumAlgorithm {
+    Crc32,
+    Md5,
+    Sha256,
+}
+
+enum ChecksumState {
+    Crc32(Crc32Hasher),
+    Md5(Md5),
+    Sha256(Sha256),
+}
+
+/// ChecksumVerify computes a rolling checksum over the bytes that flow
+/// through it and compares it against an `expected` digest once the inner
+/// reader reaches EOF, returning an error on mismatch.
+///
+/// It passes bytes through unchanged, so it can be composed before or
+/// after a decompression transform depending on whether the checksum was
+/// taken over the compressed or decompressed data.
+pub struct ChecksumVerify {
+    state: ChecksumState,
+    expected: Vec<u8>,
+}
+
+impl ChecksumVerify {
+    /// Create a new checksum verifier for `algorithm`, checked against
+    /// `expected` (hex-independent raw digest bytes) at EOF.
+    pub fn new(algorithm: ChecksumAlgorithm, expected: Vec<u8>) -> Self {
+        let state = match algorithm {
+            ChecksumAlgorithm::Crc32 => ChecksumState::Crc32(Crc32Hasher::new()),
+            ChecksumAlgorithm::Md5 => ChecksumState::Md5(Md5::new()),
+            ChecksumAlgorithm::Sha256 => ChecksumState::Sha256(Sha256::new()),
+        };
+
+        Self { state, expected }
+    }
+
+    fn digest(&self) -> Vec<u8> {
+        match &self.state {
+            ChecksumState::Crc32(h) => h.clone().finalize().to_be_bytes().to_vec(),
+            ChecksumState::Md5(h) => h.clone().finalize().to_vec(),
+            ChecksumState::Sha256(h) => h.clone().finalize().to_vec(),
+        }
+    }
+}
+
+impl Transform for ChecksumVerify {
+    fn transform(&mut self, input: &[u8], out: &mut BytesMut) -> io::Result<()> {
+        match &mut self.state {
+            ChecksumState::Crc32(h) => h.update(input),
+            ChecksumState::Md5(h) => h.update(input),
+            ChecksumState::Sha256(h) => h.update(input),
+        }
+
+        out.put_slice(input);
+        Ok(())
+    }
+
+    fn finish(&mut self, _out: &mut BytesMut) -> io::Result<()> {
+        let actual = self.digest();
+        if actual != self.expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "checksum mismatch at EOF",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Inspect runs a user-provided callback with every chunk of bytes that
+/// flows through it, passing the bytes through unchanged. Useful for
+/// progress reporting.
+pub struct Inspect<F> {
+    f: F,
+}
+
+impl<F> Inspect<F>
+where
+    F: FnMut(&[u8]) + Send + Sync,
+{
+    /// Create a new `Inspect` transform calling `f` with every chunk seen.
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+impl<F> Transform for Inspect<F>
+where
+    F: FnMut(&[u8]) + Send + Sync,
+{
+    fn transform(&mut self, input: &[u8], out: &mut BytesMut) -> io::Result<()> {
+        (self.f)(input);
+        out.put_slice(input);
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright 2022 Datafuse Labs.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::raw::BytesRange;
+
+/// Default chunk size used to convert a non-streamable reader into a
+/// `Stream<Bytes>`, matching the size of each `Bytes` yielded from
+/// `poll_next` as well as the scratch buffer used to fill it.
+const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Args for `read` operation.
+#[derive(Debug, Clone, Default)]
+pub struct OpRead {
+    range: BytesRange,
+    prefetch: Option<(usize, usize)>,
+    chunk_size: Option<usize>,
+}
+
+impl OpRead {
+    /// Create a new `OpRead` reading the whole object.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the range for this operation.
+    pub fn with_range(mut self, range: impl Into<BytesRange>) -> Self {
+        self.range = range.into();
+        self
+    }
+
+    /// Get the range for this operation.
+    pub fn range(&self) -> BytesRange {
+        self.range
+    }
+
+    /// Enable background read-ahead for this read, keeping up to `chunks`
+    /// range requests of `chunk_size` bytes each in flight ahead of the
+    /// current read position.
+    ///
+    /// This is off by default: it only pays off for HTTP-backed services
+    /// where each range read is a network round-trip, and a ranged request
+    /// already covering the whole read doesn't benefit from it.
+    pub fn with_prefetch(mut self, chunks: usize, chunk_size: usize) -> Self {
+        self.prefetch = Some((chunks, chunk_size));
+        self
+    }
+
+    /// Get the configured prefetch window as `(chunks, chunk_size)`, if any.
+    pub fn prefetch(&self) -> Option<(usize, usize)> {
+        self.prefetch
+    }
+
+    /// Tune the `Bytes` granularity produced when a non-streamable reader
+    /// is converted into a `Stream<Bytes>`.
+    ///
+    /// Workloads feeding CPU-bound workers want large chunks to amortize
+    /// handoff, while latency-sensitive streaming wants small chunks, so a
+    /// single global default can't serve both. Defaults to 256KiB.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Get the configured chunk size, falling back to the default.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE)
+    }
+}